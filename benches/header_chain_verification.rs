@@ -0,0 +1,67 @@
+//! Benchmarks comparing `Header::verify_sub_chain` (the whole chain materialized as a
+//! slice) against `Header::verify_stream` (blocks threaded through one at a time from an
+//! iterator) across chain lengths, so the streaming verifier's stack-safety and throughput
+//! are demonstrable and protected against regression.
+
+use blockchain_from_scratch::c1_state_machine::p2_laundry_machine::{
+    ClothesAction, ClothesMachine, ClothesState,
+};
+use blockchain_from_scratch::c1_state_machine::StateMachine;
+use blockchain_from_scratch::c2_blockchain::p1_header_chain::{Block, Header};
+use blockchain_from_scratch::c2_blockchain::p4_consensus::Pow;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+const CHAIN_LENGTHS: [u64; 3] = [1_000, 100_000, 1_000_000];
+
+/// Mine a chain of `len` `ClothesMachine` blocks on top of a fresh genesis header, cycling
+/// wear/wash/dry. Every action costs one unit of life, so the clothes start with `u64::MAX`
+/// life to keep them from tattering out partway through a long chain.
+fn build_chain(len: u64) -> (Header, ClothesState, Vec<Block<ClothesMachine>>) {
+    let genesis = Header::genesis();
+    let pow = Pow { threshold: u64::MAX };
+    let starting_state = ClothesState::Clean(u64::MAX);
+    let cycle = [ClothesAction::Wear, ClothesAction::Wash, ClothesAction::Dry];
+
+    let mut state = starting_state.clone();
+    let mut parent = genesis.clone();
+    let mut chain = Vec::with_capacity(len as usize);
+
+    for i in 0..len {
+        let action = cycle[(i as usize) % cycle.len()].clone();
+        let post_state = ClothesMachine::next_state(&state, &action);
+        let block = parent.mine_child::<ClothesMachine, _>(state, vec![action], &pow);
+        parent = block.header.clone();
+        chain.push(block);
+        state = post_state;
+    }
+
+    (genesis, starting_state, chain)
+}
+
+fn bench_verifiers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("verify_header_chain");
+
+    for len in CHAIN_LENGTHS {
+        let (genesis, starting_state, chain) = build_chain(len);
+        let pow = Pow { threshold: u64::MAX };
+
+        group.bench_with_input(BenchmarkId::new("slice", len), &chain, |b, chain| {
+            b.iter(|| {
+                genesis.verify_sub_chain::<ClothesMachine, _>(starting_state.clone(), chain, &pow)
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("stream", len), &chain, |b, chain| {
+            b.iter_batched(
+                || chain.clone().into_iter(),
+                |iter| genesis.verify_stream::<ClothesMachine, _, _>(starting_state.clone(), iter, &pow),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_verifiers);
+criterion_main!(benches);
@@ -0,0 +1,145 @@
+//! A state machine is anything with a current state and a rule for transitioning to a new
+//! state given some action (or "transition"). This chapter is about capturing that idea as
+//! a trait so we can write several different machines against the same interface.
+
+use std::collections::HashSet;
+
+pub mod p2_laundry_machine;
+
+/// A minimal state machine: a type of state, a type of transition, and a pure function
+/// from (state, transition) to the next state.
+pub trait StateMachine {
+    /// The type of state this machine can be in.
+    type State;
+    /// The type of transitions that can be applied to this machine's states.
+    type Transition;
+
+    /// Calculate the resulting state when the given transition is applied to the given state.
+    fn next_state(starting_state: &Self::State, t: &Self::Transition) -> Self::State;
+
+    /// Fold `transitions` through `next_state` one at a time, returning only the final
+    /// state. This is exactly what `Header::child` does to compute a block's `state_root`,
+    /// pulled out here so any transition log can be replayed the same way.
+    fn replay(start: &Self::State, transitions: &[Self::Transition]) -> Self::State
+    where
+        Self::State: Clone,
+    {
+        transitions
+            .iter()
+            .fold(start.clone(), |state, t| Self::next_state(&state, t))
+    }
+
+    /// Like `replay`, but returns every state visited along the way: `start`, then the
+    /// state after each transition in order, so a machine's whole history can be
+    /// reconstructed and inspected rather than collapsed to just its final state.
+    fn trace(start: &Self::State, transitions: &[Self::Transition]) -> Vec<Self::State>
+    where
+        Self::State: Clone,
+    {
+        let mut states = Vec::with_capacity(transitions.len() + 1);
+        states.push(start.clone());
+
+        for t in transitions {
+            let next = Self::next_state(states.last().expect("states is never empty"), t);
+            states.push(next);
+        }
+
+        states
+    }
+
+    /// Breadth-first explore the transition graph rooted at `start`, applying every
+    /// transition in `actions` to every state reached so far, up to `depth` rounds, and
+    /// return the distinct set of states encountered (including `start` itself).
+    fn reachable_states(
+        start: &Self::State,
+        actions: &[Self::Transition],
+        depth: u32,
+    ) -> HashSet<Self::State>
+    where
+        Self::State: Clone + Eq + std::hash::Hash,
+    {
+        let mut seen = HashSet::new();
+        seen.insert(start.clone());
+
+        let mut frontier = vec![start.clone()];
+        for _ in 0..depth {
+            let mut next_frontier = Vec::new();
+            for state in &frontier {
+                for action in actions {
+                    let next = Self::next_state(state, action);
+                    if seen.insert(next.clone()) {
+                        next_frontier.push(next);
+                    }
+                }
+            }
+
+            if next_frontier.is_empty() {
+                break;
+            }
+            frontier = next_frontier;
+        }
+
+        seen
+    }
+}
+
+#[test]
+fn sm_1_replay_folds_transitions() {
+    use p2_laundry_machine::{ClothesAction, ClothesMachine, ClothesState};
+
+    let start = ClothesState::Clean(3);
+    let end = ClothesMachine::replay(&start, &[ClothesAction::Wear, ClothesAction::Wash]);
+
+    assert_eq!(end, ClothesState::Wet(1));
+}
+
+#[test]
+fn sm_1_trace_returns_every_intermediate_state() {
+    use p2_laundry_machine::{ClothesAction, ClothesMachine, ClothesState};
+
+    let start = ClothesState::Clean(3);
+    let trace = ClothesMachine::trace(&start, &[ClothesAction::Wear, ClothesAction::Wash]);
+
+    assert_eq!(
+        trace,
+        vec![
+            ClothesState::Clean(3),
+            ClothesState::Dirty(2),
+            ClothesState::Wet(1),
+        ]
+    );
+}
+
+#[test]
+fn sm_1_reachable_states_includes_start_and_is_bounded_by_depth() {
+    use p2_laundry_machine::{ClothesAction, ClothesMachine, ClothesState};
+
+    let start = ClothesState::Clean(2);
+    let actions = [ClothesAction::Wear, ClothesAction::Wash, ClothesAction::Dry];
+
+    let zero_depth = ClothesMachine::reachable_states(&start, &actions, 0);
+    assert_eq!(zero_depth, HashSet::from([start.clone()]));
+
+    let one_step = ClothesMachine::reachable_states(&start, &actions, 1);
+    assert_eq!(
+        one_step,
+        HashSet::from([
+            ClothesState::Clean(2),
+            ClothesState::Dirty(1),
+            ClothesState::Wet(1),
+            ClothesState::Clean(1),
+        ])
+    );
+}
+
+#[test]
+fn sm_1_reachable_states_converges_on_tattered() {
+    use p2_laundry_machine::{ClothesAction, ClothesMachine, ClothesState};
+
+    let start = ClothesState::Clean(1);
+    let actions = [ClothesAction::Wear, ClothesAction::Wash, ClothesAction::Dry];
+
+    let reached = ClothesMachine::reachable_states(&start, &actions, 5);
+
+    assert!(reached.contains(&ClothesState::Tattered));
+}
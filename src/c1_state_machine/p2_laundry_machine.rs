@@ -9,7 +9,7 @@ use super::StateMachine;
 pub struct ClothesMachine;
 
 /// Models a piece of clothing throughout its lifecycle.
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
 pub enum ClothesState {
     /// Clean clothes ready to be worn. With some given life left.
     Clean(u64),
@@ -35,6 +35,7 @@ impl ClothesState {
 }
 
 /// Something you can do with clothes
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
 pub enum ClothesAction {
     /// Wearing clothes decreases their life by 1 and makes them dirty.
     Wear,
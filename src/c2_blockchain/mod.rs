@@ -0,0 +1,9 @@
+//! A blockchain is fundamentally a hash-linked data structure of headers, each one
+//! committing to a parent, a height, and (eventually) a body of data. This chapter builds
+//! that structure up from a bare linear list of headers into a fork-aware store with its
+//! own consensus and execution rules.
+
+pub mod p1_header_chain;
+pub mod p2_chain_store;
+pub mod p3_merkle_extrinsics;
+pub mod p4_consensus;
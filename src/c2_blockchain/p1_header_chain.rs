@@ -3,6 +3,9 @@
 //! start with that.
 //!
 
+use crate::c1_state_machine::StateMachine;
+use crate::c2_blockchain::p3_merkle_extrinsics::merkle_root;
+use crate::c2_blockchain::p4_consensus::Consensus;
 use crate::hash;
 
 // We will use Rust's built-in hashing where the output type is u64. I'll make an alias
@@ -12,69 +15,248 @@ type Hash = u64;
 /// The most basic blockchain header possible. We learned its basic structure from lecture.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Header {
-    parent: Hash,
-    height: u64,
-    // We know from the lecture that we will probably need these, but we don't need them yet.
-    extrinsics_root: (),
-    state_root: (),
-    consensus_digest: (),
+    pub(crate) parent: Hash,
+    pub(crate) height: u64,
+    pub(crate) extrinsics_root: Hash,
+    pub(crate) state_root: Hash,
+    /// The nonce a `Consensus` mines into this header to seal it.
+    pub(crate) consensus_digest: u64,
+}
+
+/// A header paired with the extrinsics that were executed to produce its `state_root`,
+/// and that its `extrinsics_root` commits to. This is the "services as state machines"
+/// idea made concrete: the chain records the whole sequence of transitions, not just
+/// hash-linked empty headers, so the current state of `SM` can always be reconstructed,
+/// and any single extrinsic's inclusion can be proven without the rest of the body.
+pub struct Block<SM: StateMachine> {
+    pub header: Header,
+    pub extrinsics: Vec<SM::Transition>,
+}
+
+impl<SM: StateMachine> Clone for Block<SM>
+where
+    SM::Transition: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            header: self.header.clone(),
+            extrinsics: self.extrinsics.clone(),
+        }
+    }
 }
 
 // Here are the methods for creating a new header and verifying headers.
 // It is your job to write them.
 impl Header {
     /// Returns a new valid genesis header.
-    fn genesis() -> Self {
+    pub fn genesis() -> Self {
         Self {
             parent: 0,
             height: 0,
-            extrinsics_root: (),
-            state_root: (),
-            consensus_digest: (),
+            extrinsics_root: 0,
+            state_root: 0,
+            consensus_digest: 0,
         }
     }
 
-    /// Create and return a valid child header.
-    fn child(&self) -> Self {
-        Self {
-            parent: hash(&self),
+    /// Build a valid, but not yet sealed, child block. `pre_state` is the state inherited
+    /// from this header, and `extrinsics` is the body of the new block: it is folded
+    /// through `SM::next_state` one extrinsic at a time to produce `state_root`, and
+    /// committed to as a Merkle tree to produce `extrinsics_root`.
+    fn child<SM: StateMachine>(
+        &self,
+        pre_state: SM::State,
+        extrinsics: Vec<SM::Transition>,
+    ) -> Block<SM>
+    where
+        SM::State: std::hash::Hash,
+        SM::Transition: std::hash::Hash,
+    {
+        let post_state = extrinsics
+            .iter()
+            .fold(pre_state, |state, t| SM::next_state(&state, t));
+
+        let header = Self {
+            parent: hash(self),
             height: self.height + 1,
-            ..self.clone()
+            extrinsics_root: merkle_root(&extrinsics),
+            state_root: hash(&post_state),
+            consensus_digest: 0,
+        };
+
+        Block { header, extrinsics }
+    }
+
+    /// Build and mine a valid child block: the same as `child`, but with its header sealed
+    /// by `consensus` before it is returned.
+    pub fn mine_child<SM: StateMachine, C: Consensus>(
+        &self,
+        pre_state: SM::State,
+        extrinsics: Vec<SM::Transition>,
+        consensus: &C,
+    ) -> Block<SM>
+    where
+        SM::State: std::hash::Hash,
+        SM::Transition: std::hash::Hash,
+    {
+        let mut block = self.child::<SM>(pre_state, extrinsics);
+        block.header = consensus.seal(block.header);
+        block
+    }
+
+    /// Verify that all the given blocks form a valid chain from this header to the tip
+    /// under `consensus`, starting execution from `pre_state`. An "entire" chain can be
+    /// verified by calling this method on a genesis header with the state machine's
+    /// starting state. This method may assume that the block on which it is called is
+    /// valid, but it must verify all of the blocks in the slice: each block's seal under
+    /// `consensus`, its `extrinsics_root` against its recomputed Merkle root, and its
+    /// `state_root` against the state produced by re-executing its extrinsics.
+    ///
+    /// Written as an iterative loop over the slice, rather than recursion, so it doesn't
+    /// risk blowing the stack on very long chains.
+    pub fn verify_sub_chain<SM: StateMachine, C: Consensus>(
+        &self,
+        pre_state: SM::State,
+        chain: &[Block<SM>],
+        consensus: &C,
+    ) -> bool
+    where
+        SM::State: std::hash::Hash,
+        SM::Transition: std::hash::Hash,
+    {
+        let mut expected_parent = hash(self);
+        let mut expected_height = self.height + 1;
+        let mut state = pre_state;
+
+        for block in chain {
+            match verify_one::<SM, C>(expected_parent, expected_height, state, block, consensus) {
+                Some(post_state) => state = post_state,
+                None => return false,
+            }
+
+            expected_parent = hash(&block.header);
+            expected_height = block.header.height + 1;
         }
+
+        true
     }
 
-    /// Verify that all the given headers form a valid chain from this header to the tip.
-    /// An "entire" chain can be verified by calling this method on a genesis header.
-    /// This method may assume that the block on which it is called is valid, but it
-    /// must verify all of the blocks in the slice;
-    fn verify_sub_chain(&self, chain: &[Header]) -> bool {
-        if chain.len() == 0 {
-            true
-        } else {
-            hash(&self) == chain[0].parent
-                && self.height + 1 == chain[0].height
-                && chain[0].verify_sub_chain(&chain[1..])
+    /// Verify a chain of blocks arriving from an `Iterator` rather than a materialized
+    /// `Vec`, threading the expected parent hash, height, and state through the iterator
+    /// one block at a time. This lets arbitrarily long chains be validated as they stream
+    /// in from a reader without ever holding more than one block in memory.
+    pub fn verify_stream<SM: StateMachine, C: Consensus, I: Iterator<Item = Block<SM>>>(
+        &self,
+        pre_state: SM::State,
+        iter: I,
+        consensus: &C,
+    ) -> bool
+    where
+        SM::State: std::hash::Hash,
+        SM::Transition: std::hash::Hash,
+    {
+        let mut expected_parent = hash(self);
+        let mut expected_height = self.height + 1;
+        let mut state = pre_state;
+
+        for block in iter {
+            match verify_one::<SM, C>(expected_parent, expected_height, state, &block, consensus) {
+                Some(post_state) => state = post_state,
+                None => return false,
+            }
+
+            expected_parent = hash(&block.header);
+            expected_height = block.header.height + 1;
         }
+
+        true
     }
 }
 
-// And finally a few functions to use the code we just
+/// Check a single block against the parent hash and height it's expected to build on,
+/// its consensus seal, its Merkle-committed extrinsics, and the state it's expected to
+/// produce, returning that resulting state on success. Shared by `verify_sub_chain` and
+/// `verify_stream` so the two only differ in how they get their blocks, not in how a
+/// block is checked.
+fn verify_one<SM: StateMachine, C: Consensus>(
+    expected_parent: Hash,
+    expected_height: u64,
+    state: SM::State,
+    block: &Block<SM>,
+    consensus: &C,
+) -> Option<SM::State>
+where
+    SM::State: std::hash::Hash,
+    SM::Transition: std::hash::Hash,
+{
+    let post_state = block
+        .extrinsics
+        .iter()
+        .fold(state, |state, t| SM::next_state(&state, t));
+
+    let valid = expected_parent == block.header.parent
+        && expected_height == block.header.height
+        && consensus.verify_seal(&block.header)
+        && merkle_root(&block.extrinsics) == block.header.extrinsics_root
+        && hash(&post_state) == block.header.state_root;
+
+    valid.then_some(post_state)
+}
+
+// And finally a few functions to use the code we just wrote, all exercised against
+// `ClothesMachine`, the one pluggable runtime we have so far.
+
+#[cfg(test)]
+use crate::c1_state_machine::p2_laundry_machine::{ClothesAction, ClothesMachine, ClothesState};
+#[cfg(test)]
+use crate::c2_blockchain::p4_consensus::Pow;
+
+/// Most tests below only care about header chaining, execution, and the Merkle commitment,
+/// not about proof-of-work difficulty, so they mine under a threshold that anything clears.
+#[cfg(test)]
+fn any_pow() -> Pow {
+    Pow { threshold: u64::MAX }
+}
+
+/// Build and return a valid chain of `ClothesMachine` blocks with exactly five blocks
+/// (including the genesis block), plus the starting state the chain began from.
+#[cfg(test)]
+fn build_valid_chain_length_5() -> (Header, ClothesState, Vec<Block<ClothesMachine>>) {
+    let genesis = Header::genesis();
+    let starting_state = ClothesState::Clean(100);
+    let pow = any_pow();
 
-/// Build and return a valid chain with exactly five blocks including the genesis block.
-fn build_valid_chain_length_5() -> Vec<Header> {
-    let mut chain = vec![Header::genesis()];
-    for _ in 0..4 {
-        chain.push(chain.last().unwrap().child());
+    let actions = [
+        ClothesAction::Wear,
+        ClothesAction::Wash,
+        ClothesAction::Dry,
+        ClothesAction::Wear,
+    ];
+
+    let mut state = starting_state.clone();
+    let mut parent = genesis.clone();
+    let mut chain = Vec::new();
+
+    for action in actions {
+        let post_state = ClothesMachine::next_state(&state, &action);
+        let block = parent.mine_child::<ClothesMachine, _>(state, vec![action], &pow);
+        parent = block.header.clone();
+        chain.push(block);
+        state = post_state;
     }
 
-    chain
+    (genesis, starting_state, chain)
 }
 
-/// Build and return a chain with at least three headers.
-/// The chain should start with a proper genesis header,
-/// but the entire chain should NOT be valid.
-fn build_an_invalid_chain() -> Vec<Header> {
-    vec![Header::genesis(), Header::genesis(), Header::genesis()]
+/// Build and return a chain with at least three blocks, starting with a proper genesis
+/// header, whose entire chain is NOT valid: the second block's recorded `state_root`
+/// does not match its transitions.
+#[cfg(test)]
+fn build_an_invalid_chain() -> (Header, ClothesState, Vec<Block<ClothesMachine>>) {
+    let (genesis, starting_state, mut chain) = build_valid_chain_length_5();
+    chain[1].header.state_root = hash(&"not the real post-state");
+
+    (genesis, starting_state, chain)
 }
 
 // To run these tests: `cargo test bc_1
@@ -93,31 +275,48 @@ fn bc_1_genesis_block_parent() {
 #[test]
 fn bc_1_child_block_height() {
     let g = Header::genesis();
-    let b1 = g.child();
-    assert!(b1.height == 1);
+    let b1 = g.mine_child::<ClothesMachine, _>(ClothesState::Clean(10), vec![], &any_pow());
+    assert!(b1.header.height == 1);
 }
 
 #[test]
 fn bc_1_child_block_parent() {
     let g = Header::genesis();
-    let b1 = g.child();
-    assert!(b1.parent == hash(&g));
+    let b1 = g.mine_child::<ClothesMachine, _>(ClothesState::Clean(10), vec![], &any_pow());
+    assert!(b1.header.parent == hash(&g));
+}
+
+#[test]
+fn bc_1_child_block_state_root() {
+    let g = Header::genesis();
+    let b1 = g.mine_child::<ClothesMachine, _>(
+        ClothesState::Clean(10),
+        vec![ClothesAction::Wear],
+        &any_pow(),
+    );
+    let expected = ClothesMachine::next_state(&ClothesState::Clean(10), &ClothesAction::Wear);
+    assert!(b1.header.state_root == hash(&expected));
 }
 
 #[test]
 fn bc_1_verify_genesis_only() {
     let g = Header::genesis();
 
-    assert!(g.verify_sub_chain(&[]));
+    assert!(g.verify_sub_chain::<ClothesMachine, _>(ClothesState::Clean(10), &[], &any_pow()));
 }
 
 #[test]
 fn bc_1_verify_three_blocks() {
     let g = Header::genesis();
-    let b1 = g.child();
-    let b2 = b1.child();
+    let pow = any_pow();
+    let state0 = ClothesState::Clean(10);
+    let b1 = g.mine_child::<ClothesMachine, _>(state0, vec![ClothesAction::Wear], &pow);
+    let state1 = ClothesMachine::next_state(&ClothesState::Clean(10), &ClothesAction::Wear);
+    let b2 = b1
+        .header
+        .mine_child::<ClothesMachine, _>(state1, vec![ClothesAction::Wash], &pow);
 
-    assert!(g.verify_sub_chain(&[b1, b2]));
+    assert!(g.verify_sub_chain::<ClothesMachine, _>(ClothesState::Clean(10), &[b1, b2], &pow));
 }
 
 #[test]
@@ -125,10 +324,11 @@ fn bc_1_cant_verify_invalid_height() {
     // This and following tests use the student's own verify function so as
     // not to give away the solution to writing that function.
     let g = Header::genesis();
-    let mut b1 = g.child();
-    b1.height = 10;
+    let pow = any_pow();
+    let mut b1 = g.mine_child::<ClothesMachine, _>(ClothesState::Clean(10), vec![], &pow);
+    b1.header.height = 10;
 
-    assert!(!g.verify_sub_chain(&[b1]))
+    assert!(!g.verify_sub_chain::<ClothesMachine, _>(ClothesState::Clean(10), &[b1], &pow))
 }
 
 #[test]
@@ -136,24 +336,86 @@ fn bc_1_cant_verify_invalid_parent() {
     // This test chooses to use the student's own verify function so as
     // not to give away the solution to writing that function.
     let g = Header::genesis();
-    let mut b1 = g.child();
-    b1.parent = 10;
+    let pow = any_pow();
+    let mut b1 = g.mine_child::<ClothesMachine, _>(ClothesState::Clean(10), vec![], &pow);
+    b1.header.parent = 10;
+
+    assert!(!g.verify_sub_chain::<ClothesMachine, _>(ClothesState::Clean(10), &[b1], &pow))
+}
+
+#[test]
+fn bc_1_cant_verify_invalid_state_root() {
+    let g = Header::genesis();
+    let pow = any_pow();
+    let mut b1 =
+        g.mine_child::<ClothesMachine, _>(ClothesState::Clean(10), vec![ClothesAction::Wear], &pow);
+    b1.header.state_root = hash(&"not the real post-state");
+
+    assert!(!g.verify_sub_chain::<ClothesMachine, _>(ClothesState::Clean(10), &[b1], &pow))
+}
+
+#[test]
+fn bc_1_cant_verify_invalid_extrinsics_root() {
+    let g = Header::genesis();
+    let pow = any_pow();
+    let mut b1 =
+        g.mine_child::<ClothesMachine, _>(ClothesState::Clean(10), vec![ClothesAction::Wear], &pow);
+    b1.header.extrinsics_root = hash(&"not the real extrinsics");
+
+    assert!(!g.verify_sub_chain::<ClothesMachine, _>(ClothesState::Clean(10), &[b1], &pow))
+}
+
+#[test]
+fn bc_1_cant_verify_unmined_block() {
+    let g = Header::genesis();
+    // A block that was never sealed, checked against a threshold that requires real work.
+    let mut b1 = g.mine_child::<ClothesMachine, _>(ClothesState::Clean(10), vec![], &any_pow());
+    b1.header.consensus_digest = 0;
 
-    assert!(!g.verify_sub_chain(&[b1]))
+    let strict_pow = Pow { threshold: 1 };
+    assert!(!g.verify_sub_chain::<ClothesMachine, _>(
+        ClothesState::Clean(10),
+        &[b1],
+        &strict_pow
+    ))
 }
 
 #[test]
 fn bc_1_verify_chain_length_five() {
     // This test chooses to use the student's own verify function.
     // This should be relatively safe given that we have already tested that function.
-    let chain = build_valid_chain_length_5();
-    assert!(chain[0].verify_sub_chain(&chain[1..]))
+    let (genesis, starting_state, chain) = build_valid_chain_length_5();
+    assert!(genesis.verify_sub_chain::<ClothesMachine, _>(starting_state, &chain, &any_pow()))
 }
 
 #[test]
 fn bc_1_invalid_chain_is_really_invalid() {
     // This test chooses to use the student's own verify function.
     // This should be relatively safe given that we have already tested that function.
-    let invalid_chain = build_an_invalid_chain();
-    assert!(!invalid_chain[0].verify_sub_chain(&invalid_chain[1..]))
+    let (genesis, starting_state, invalid_chain) = build_an_invalid_chain();
+    assert!(!genesis.verify_sub_chain::<ClothesMachine, _>(
+        starting_state,
+        &invalid_chain,
+        &any_pow()
+    ))
+}
+
+#[test]
+fn bc_1_verify_stream_accepts_valid_chain() {
+    let (genesis, starting_state, chain) = build_valid_chain_length_5();
+    assert!(genesis.verify_stream::<ClothesMachine, _, _>(
+        starting_state,
+        chain.into_iter(),
+        &any_pow()
+    ))
+}
+
+#[test]
+fn bc_1_verify_stream_rejects_invalid_chain() {
+    let (genesis, starting_state, invalid_chain) = build_an_invalid_chain();
+    assert!(!genesis.verify_stream::<ClothesMachine, _, _>(
+        starting_state,
+        invalid_chain.into_iter(),
+        &any_pow()
+    ))
 }
@@ -0,0 +1,110 @@
+//! `extrinsics_root` on `Header` is a commitment over a block's extrinsics. This module
+//! builds that commitment as a binary Merkle tree: hash each leaf,
+//! then repeatedly hash adjacent pairs up the tree (duplicating the last node when a level
+//! has an odd count) until a single root remains. It also provides inclusion proofs, so a
+//! light client holding only the root can verify a single extrinsic belongs to the body
+//! without downloading the whole thing.
+
+use crate::hash;
+
+type Hash = u64;
+
+/// Compute the Merkle root over `leaves`. Each leaf is hashed with `hash(&leaf)`, then
+/// adjacent hashes are combined with `hash(&(left, right))` one level at a time,
+/// duplicating the last hash of a level when its length is odd. An empty body has no
+/// leaves and hashes to `0`.
+pub fn merkle_root<T: std::hash::Hash>(leaves: &[T]) -> Hash {
+    if leaves.is_empty() {
+        return 0;
+    }
+
+    let mut level: Vec<Hash> = leaves.iter().map(hash).collect();
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(*level.last().unwrap());
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| hash(&(pair[0], pair[1])))
+            .collect();
+    }
+
+    level[0]
+}
+
+/// Build an inclusion proof for the leaf at `index`: the sibling hash needed at each level
+/// on the way up to the root, paired with whether that sibling sits to the left of the
+/// node being proven.
+pub fn merkle_proof<T: std::hash::Hash>(leaves: &[T], index: usize) -> Vec<(Hash, bool)> {
+    let mut level: Vec<Hash> = leaves.iter().map(hash).collect();
+    let mut idx = index;
+    let mut proof = Vec::new();
+
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(*level.last().unwrap());
+        }
+
+        let sibling_is_left = idx % 2 == 1;
+        let sibling_idx = if sibling_is_left { idx - 1 } else { idx + 1 };
+        proof.push((level[sibling_idx], sibling_is_left));
+
+        level = level
+            .chunks(2)
+            .map(|pair| hash(&(pair[0], pair[1])))
+            .collect();
+        idx /= 2;
+    }
+
+    proof
+}
+
+/// Recompute the root implied by `leaf` and its `proof`, and check it against `root`.
+pub fn verify_proof<T: std::hash::Hash>(leaf: &T, proof: &[(Hash, bool)], root: Hash) -> bool {
+    let mut current = hash(leaf);
+
+    for (sibling, sibling_is_left) in proof {
+        current = if *sibling_is_left {
+            hash(&(*sibling, current))
+        } else {
+            hash(&(current, *sibling))
+        };
+    }
+
+    current == root
+}
+
+#[test]
+fn bc_3_empty_body_root_is_zero() {
+    let leaves: Vec<u32> = vec![];
+    assert_eq!(merkle_root(&leaves), 0);
+}
+
+#[test]
+fn bc_3_single_leaf_proof_verifies() {
+    let leaves = vec!["only extrinsic"];
+    let root = merkle_root(&leaves);
+    let proof = merkle_proof(&leaves, 0);
+
+    assert!(verify_proof(&leaves[0], &proof, root));
+}
+
+#[test]
+fn bc_3_every_leaf_in_odd_sized_body_proves_inclusion() {
+    let leaves = vec![1u32, 2, 3, 4, 5];
+    let root = merkle_root(&leaves);
+
+    for (i, leaf) in leaves.iter().enumerate() {
+        let proof = merkle_proof(&leaves, i);
+        assert!(verify_proof(leaf, &proof, root));
+    }
+}
+
+#[test]
+fn bc_3_tampered_leaf_fails_proof() {
+    let leaves = vec![1u32, 2, 3, 4];
+    let root = merkle_root(&leaves);
+    let proof = merkle_proof(&leaves, 2);
+
+    assert!(!verify_proof(&99u32, &proof, root));
+}
@@ -0,0 +1,135 @@
+//! `consensus_digest` on `Header` holds whatever a chain's consensus mechanism needs to
+//! stash on a header to prove it was produced legitimately. This module
+//! defines a `Consensus` trait for that, and a simple proof-of-work implementor that mines
+//! a `nonce` into `consensus_digest` until the header's hash falls under a `threshold`.
+
+use super::p1_header_chain::Header;
+use crate::hash;
+
+/// Something that can seal a header (stamp it with a valid `consensus_digest`) and verify
+/// that an existing header's seal is valid. Swapping the `Consensus` a chain uses swaps its
+/// entire consensus mechanism without touching the header-chaining or execution logic.
+pub trait Consensus {
+    /// Seal `header`, filling in its `consensus_digest` so that `verify_seal` accepts it.
+    fn seal(&self, header: Header) -> Header;
+
+    /// Check whether `header`'s `consensus_digest` is a valid seal for it.
+    fn verify_seal(&self, header: &Header) -> bool;
+}
+
+/// Proof-of-work consensus: a header is validly sealed when its hash, nonce included, is
+/// below `threshold`. Lower thresholds mean fewer valid hashes and therefore more work.
+pub struct Pow {
+    pub threshold: u64,
+}
+
+impl Consensus for Pow {
+    fn seal(&self, mut header: Header) -> Header {
+        assert!(
+            self.threshold > 0,
+            "Pow::seal: threshold of 0 can never be satisfied, mining would never terminate"
+        );
+
+        header.consensus_digest = 0;
+        while hash(&header) >= self.threshold {
+            header.consensus_digest += 1;
+        }
+
+        header
+    }
+
+    fn verify_seal(&self, header: &Header) -> bool {
+        hash(header) < self.threshold
+    }
+}
+
+/// A `Pow` that retargets its `threshold` every so often based on how much work recent
+/// blocks actually took to mine, so that difficulty can track changes in mining power over
+/// time the way a real client's consensus would.
+pub struct PowRetarget {
+    pub threshold: u64,
+    /// How many of the most recently sealed headers to retarget from.
+    pub retarget_period: u64,
+}
+
+impl PowRetarget {
+    /// Retarget against `recent`, the `consensus_digest` (nonce) of the last
+    /// `retarget_period` sealed headers. If, on average, it took more tries than
+    /// `target_avg_nonce` to seal a block, mining has been too hard and the threshold is
+    /// loosened (raised); if it took fewer, the threshold is tightened (lowered).
+    pub fn retarget(&self, recent: &[Header], target_avg_nonce: u64) -> Pow {
+        if recent.is_empty() {
+            return Pow {
+                threshold: self.threshold,
+            };
+        }
+
+        let avg_nonce =
+            (recent.iter().map(|h| h.consensus_digest).sum::<u64>() / recent.len() as u64).max(1);
+
+        Pow {
+            threshold: (self.threshold.saturating_mul(avg_nonce) / target_avg_nonce.max(1)).max(1),
+        }
+    }
+}
+
+#[test]
+fn bc_4_mined_header_verifies() {
+    let header = Header::genesis();
+    let pow = Pow { threshold: u64::MAX / 2 };
+
+    let sealed = pow.seal(header);
+
+    assert!(pow.verify_seal(&sealed));
+}
+
+#[test]
+fn bc_4_tampered_digest_fails_verification() {
+    let header = Header::genesis();
+    let pow = Pow { threshold: u64::MAX / 2 };
+
+    let mut sealed = pow.seal(header);
+    // Pick a stricter threshold that the nonce we just mined almost certainly doesn't meet.
+    let stricter = Pow { threshold: 1 };
+    assert!(!stricter.verify_seal(&sealed));
+
+    sealed.consensus_digest = sealed.consensus_digest.wrapping_add(1);
+    assert!(!stricter.verify_seal(&sealed));
+}
+
+#[test]
+fn bc_4_retarget_tightens_when_mining_is_too_easy() {
+    let pow = PowRetarget {
+        threshold: 1_000,
+        retarget_period: 2,
+    };
+    let mut fast_header = Header::genesis();
+    fast_header.consensus_digest = 1;
+    let recent = vec![fast_header.clone(), fast_header];
+
+    let retargeted = pow.retarget(&recent, 100);
+
+    assert!(retargeted.threshold < pow.threshold);
+}
+
+#[test]
+#[should_panic(expected = "threshold of 0")]
+fn bc_4_seal_with_zero_threshold_panics_instead_of_hanging() {
+    let pow = Pow { threshold: 0 };
+    pow.seal(Header::genesis());
+}
+
+#[test]
+fn bc_4_retarget_loosens_when_mining_is_too_hard() {
+    let pow = PowRetarget {
+        threshold: 1_000,
+        retarget_period: 2,
+    };
+    let mut slow_header = Header::genesis();
+    slow_header.consensus_digest = 1_000;
+    let recent = vec![slow_header.clone(), slow_header];
+
+    let retargeted = pow.retarget(&recent, 100);
+
+    assert!(retargeted.threshold > pow.threshold);
+}
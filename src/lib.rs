@@ -0,0 +1,17 @@
+//! A from-scratch implementation of core blockchain primitives, built up incrementally
+//! chapter by chapter: state machines first, then the chain of headers that record their
+//! transitions.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+pub mod c1_state_machine;
+pub mod c2_blockchain;
+
+/// Hash any hashable type using Rust's default (SipHash) hasher, collapsing it to a `u64`.
+/// This is the single hashing primitive used throughout the chain modules.
+pub fn hash<T: Hash>(t: &T) -> u64 {
+    let mut s = DefaultHasher::new();
+    t.hash(&mut s);
+    s.finish()
+}